@@ -1,6 +1,6 @@
 use super::ffi;
 use super::flags::SolvableFlags;
-use super::solvable::SolvableId;
+use std::collections::VecDeque;
 use std::marker::PhantomData;
 use std::os::raw::c_int;
 
@@ -13,6 +13,12 @@ pub struct Queue<T> {
     queue: ffi::Queue,
     // Makes this queue typesafe
     _data: PhantomData<T>,
+    // Tracks, in order, whether each logical entry currently in the queue occupies one raw
+    // `ffi::Id` slot (pushed via `push_id`/`push_front`) or two (the flag followed by the id,
+    // pushed via `push_id_with_flags`). Every method that counts, inspects or removes entries
+    // reads this instead of the raw slot count, so a flagged entry is never double-counted,
+    // split, or desynchronized from its flag.
+    entries: VecDeque<bool>,
 }
 
 impl<T> Default for Queue<T> {
@@ -30,6 +36,7 @@ impl<T> Default for Queue<T> {
         Self {
             queue,
             _data: PhantomData,
+            entries: VecDeque::new(),
         }
     }
 }
@@ -43,12 +50,54 @@ impl<T> Drop for Queue<T> {
     }
 }
 
+impl<T> Queue<T> {
+    /// Creates a new, empty queue with storage pre-allocated for at least `capacity` ids, so
+    /// filling it up doesn't repeatedly trigger libsolv's incremental growth
+    pub fn with_capacity(capacity: usize) -> Self {
+        let mut queue = Self::default();
+        queue.reserve(capacity);
+        queue
+    }
+
+    /// Reserves storage for at least `additional` more ids, growing the backing buffer in a
+    /// single allocation rather than one `queue_insert` call at a time
+    pub fn reserve(&mut self, additional: usize) {
+        unsafe { ffi::queue_prealloc(self.raw_ptr(), additional as c_int) };
+    }
+}
+
 impl<T> Queue<T> {
     /// Returns a raw pointer to the wrapped `ffi::Repo`, to be used for calling ffi functions
     /// that require access to the repo (and for nothing else)
     pub(super) fn raw_ptr(&mut self) -> *mut ffi::Queue {
         &mut self.queue as *mut ffi::Queue
     }
+
+    /// Returns the number of ids currently stored in the queue
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns true if the queue contains no ids
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Removes all ids from the queue
+    pub fn clear(&mut self) {
+        unsafe { ffi::queue_empty(self.raw_ptr()) };
+        self.entries.clear();
+    }
+
+    /// Returns the raw ids currently stored in the queue, in order
+    fn as_slice(&self) -> &[ffi::Id] {
+        if self.queue.elements.is_null() {
+            &[]
+        } else {
+            // Safe because the slice never outlives `self` and never extends past `count`
+            unsafe { std::slice::from_raw_parts(self.queue.elements, self.queue.count as usize) }
+        }
+    }
 }
 
 impl<T: Into<ffi::Id>> Queue<T> {
@@ -57,6 +106,7 @@ impl<T: Into<ffi::Id>> Queue<T> {
         unsafe {
             ffi::queue_insert(self.raw_ptr(), self.queue.count, id.into());
         }
+        self.entries.push_back(false);
     }
 
     /// Push an id and flag into the queue
@@ -69,36 +119,356 @@ impl<T: Into<ffi::Id>> Queue<T> {
                 id.into(),
             );
         }
+        self.entries.push_back(true);
+    }
+
+    /// Pushes a single id to the front of the queue
+    pub fn push_front(&mut self, id: T) {
+        unsafe {
+            ffi::queue_unshift(self.raw_ptr(), id.into());
+        }
+        self.entries.push_front(false);
+    }
+
+    /// Returns true if `id` is present anywhere in the queue
+    pub fn contains(&self, id: T) -> bool {
+        let target = id.into();
+        let slice = self.as_slice();
+        let mut raw_index = 0;
+        for &is_flagged in &self.entries {
+            if is_flagged {
+                raw_index += 1;
+            }
+            if slice[raw_index] == target {
+                return true;
+            }
+            raw_index += 1;
+        }
+        false
+    }
+
+    /// Removes the first occurrence of `id` from the queue, returning whether an entry was
+    /// removed.
+    ///
+    /// If the matched entry was pushed via [`Queue::push_id_with_flags`], both of its raw
+    /// slots (the flag and the id) are removed together so no dangling flag is left behind.
+    pub fn remove_first(&mut self, id: T) -> bool {
+        let target = id.into();
+        let mut raw_index = 0;
+        for (logical_index, &is_flagged) in self.entries.iter().enumerate() {
+            let id_slot = if is_flagged { raw_index + 1 } else { raw_index };
+            if self.as_slice()[id_slot] == target {
+                let width = if is_flagged { 2 } else { 1 };
+                unsafe { ffi::queue_deleten(self.raw_ptr(), raw_index as c_int, width as c_int) };
+                self.entries.remove(logical_index);
+                return true;
+            }
+            raw_index += if is_flagged { 2 } else { 1 };
+        }
+        false
+    }
+}
+
+impl<T: Into<ffi::Id> + From<ffi::Id>> Queue<T> {
+    /// Retains only the ids for which `f` returns `true`, removing the rest in-place.
+    ///
+    /// An entry pushed via [`Queue::push_id_with_flags`] is evaluated by its id half; if `f`
+    /// rejects it, both of its raw slots (the flag and the id) are removed together so no
+    /// dangling flag is left behind.
+    pub fn retain<F: FnMut(T) -> bool>(&mut self, mut f: F) {
+        let mut logical_index = 0;
+        let mut raw_index = 0;
+        while logical_index < self.entries.len() {
+            let is_flagged = self.entries[logical_index];
+            let id_slot = if is_flagged { raw_index + 1 } else { raw_index };
+            let id = self.as_slice()[id_slot];
+            let width = if is_flagged { 2 } else { 1 };
+
+            if f(T::from(id)) {
+                logical_index += 1;
+                raw_index += width;
+            } else {
+                unsafe { ffi::queue_deleten(self.raw_ptr(), raw_index as c_int, width as c_int) };
+                self.entries.remove(logical_index);
+            }
+        }
+    }
+}
+
+impl<T: Into<ffi::Id> + From<ffi::Id>> Queue<T> {
+    /// Removes and returns the id at the back of the queue, or `None` if the queue is empty.
+    ///
+    /// If the back entry was pushed via [`Queue::push_id_with_flags`], both of its raw slots
+    /// are popped and only the id half is returned.
+    pub fn pop_id(&mut self) -> Option<T> {
+        let is_flagged = self.entries.pop_back()?;
+
+        let id = unsafe { ffi::queue_pop(self.raw_ptr()) };
+        if is_flagged {
+            // Discard the flag word, now at the back
+            unsafe { ffi::queue_pop(self.raw_ptr()) };
+        }
+        Some(T::from(id))
+    }
+
+    /// Removes and returns the id at the front of the queue, or `None` if the queue is empty.
+    ///
+    /// If the front entry was pushed via [`Queue::push_id_with_flags`], both of its raw slots
+    /// are popped and only the id half is returned.
+    pub fn pop_front(&mut self) -> Option<T> {
+        let is_flagged = self.entries.pop_front()?;
+
+        if is_flagged {
+            // Discard the flag word, currently at the front
+            unsafe { ffi::queue_shift(self.raw_ptr()) };
+        }
+        let id = unsafe { ffi::queue_shift(self.raw_ptr()) };
+        Some(T::from(id))
+    }
+}
+
+impl<T: Into<ffi::Id>> Extend<T> for Queue<T> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        let iter = iter.into_iter();
+        let (lower, _) = iter.size_hint();
+        if lower > 0 {
+            self.reserve(lower);
+        }
+
+        for id in iter {
+            self.push_id(id);
+        }
+    }
+}
+
+impl<T: Into<ffi::Id>> FromIterator<T> for Queue<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut queue = Self::default();
+        queue.extend(iter);
+        queue
     }
 }
 
 /// A read-only reference to a libsolv queue
-pub struct QueueRef<'queue>(ffi::Queue, PhantomData<&'queue ffi::Queue>);
+pub struct QueueRef<'queue, T>(ffi::Queue, PhantomData<&'queue ffi::Queue>, PhantomData<T>);
 
-impl QueueRef<'_> {
+impl<'queue, T> QueueRef<'queue, T> {
     /// Construct a new `QueueRef` based on the provided `ffi::Queue`
     ///
     /// Safety: the queue must not have been freed
-    pub(super) unsafe fn from_ffi_queue<T>(_source: &T, queue: ffi::Queue) -> QueueRef {
-        QueueRef(queue, PhantomData::default())
+    pub(super) unsafe fn from_ffi_queue<S>(_source: &S, queue: ffi::Queue) -> QueueRef<'queue, T> {
+        QueueRef(queue, PhantomData::default(), PhantomData::default())
     }
+}
 
+impl<T: From<ffi::Id>> QueueRef<'_, T> {
     /// Returns an iterator over the ids of the queue
-    pub fn iter(&self) -> impl Iterator<Item = SolvableId> + '_ {
+    pub fn iter(&self) -> impl Iterator<Item = T> + '_ {
         // Safe to dereference, because we are doing so within the bounds of count
         (0..self.0.count as usize).map(|index| {
             let id = unsafe { *self.0.elements.add(index) };
-            SolvableId(id)
+            T::from(id)
         })
     }
 }
 
 #[cfg(test)]
 mod test {
-    use super::{super::pool::StringId, Queue};
+    use super::{super::flags::SolvableFlags, super::pool::StringId, Queue, QueueRef};
 
     #[test]
     fn create_queue() {
         let _queue = Queue::<StringId>::default();
     }
+
+    #[test]
+    fn pop_id_returns_ids_in_reverse_push_order_and_then_none() {
+        let mut queue = Queue::<StringId>::default();
+        queue.push_id(StringId(1));
+        queue.push_id(StringId(2));
+        queue.push_id(StringId(3));
+
+        assert_eq!(queue.len(), 3);
+        assert_eq!(queue.pop_id().map(|id| id.0), Some(3));
+        assert_eq!(queue.pop_id().map(|id| id.0), Some(2));
+        assert_eq!(queue.pop_id().map(|id| id.0), Some(1));
+        assert_eq!(queue.pop_id(), None);
+    }
+
+    #[test]
+    fn push_front_and_pop_front_behave_like_a_deque() {
+        let mut queue = Queue::<StringId>::default();
+        queue.push_id(StringId(1));
+        queue.push_front(StringId(2));
+        queue.push_front(StringId(3));
+
+        // [3, 2, 1]
+        assert_eq!(queue.pop_front().map(|id| id.0), Some(3));
+        assert_eq!(queue.pop_front().map(|id| id.0), Some(2));
+        assert_eq!(queue.pop_front().map(|id| id.0), Some(1));
+        assert_eq!(queue.pop_front(), None);
+    }
+
+    #[test]
+    fn len_is_empty_and_clear() {
+        let mut queue = Queue::<StringId>::default();
+        assert!(queue.is_empty());
+        assert_eq!(queue.len(), 0);
+
+        queue.push_id(StringId(1));
+        queue.push_id(StringId(2));
+        assert!(!queue.is_empty());
+        assert_eq!(queue.len(), 2);
+
+        queue.clear();
+        assert!(queue.is_empty());
+        assert_eq!(queue.len(), 0);
+        assert_eq!(queue.pop_id(), None);
+    }
+
+    #[test]
+    fn len_contains_and_pop_account_for_flagged_two_slot_entries() {
+        let mut queue = Queue::<StringId>::default();
+        queue.push_id(StringId(1));
+        queue.push_id_with_flags(StringId(2), SolvableFlags::empty());
+        queue.push_id(StringId(3));
+
+        // The flagged entry occupies two raw `ffi::Id` slots but must still count as one
+        // logical entry.
+        assert_eq!(queue.len(), 3);
+        assert!(!queue.is_empty());
+        assert!(queue.contains(StringId(2)));
+
+        assert_eq!(queue.pop_front().map(|id| id.0), Some(1));
+        assert_eq!(queue.pop_front().map(|id| id.0), Some(2));
+        assert_eq!(queue.pop_front().map(|id| id.0), Some(3));
+        assert_eq!(queue.pop_front(), None);
+    }
+
+    #[test]
+    fn contains_and_remove_first() {
+        let mut queue = Queue::<StringId>::default();
+        queue.push_id(StringId(1));
+        queue.push_id(StringId(2));
+        queue.push_id(StringId(3));
+
+        assert!(queue.contains(StringId(2)));
+        assert!(queue.remove_first(StringId(2)));
+        assert!(!queue.contains(StringId(2)));
+        assert!(!queue.remove_first(StringId(2)));
+
+        assert_eq!(queue.pop_front().map(|id| id.0), Some(1));
+        assert_eq!(queue.pop_front().map(|id| id.0), Some(3));
+        assert_eq!(queue.pop_front(), None);
+    }
+
+    #[test]
+    fn retain_keeps_only_matching_ids() {
+        let mut queue = Queue::<StringId>::default();
+        for i in 1..=5 {
+            queue.push_id(StringId(i));
+        }
+
+        queue.retain(|id| id.0 % 2 == 0);
+
+        assert_eq!(queue.pop_front().map(|id| id.0), Some(2));
+        assert_eq!(queue.pop_front().map(|id| id.0), Some(4));
+        assert_eq!(queue.pop_front(), None);
+    }
+
+    #[test]
+    fn remove_first_removes_both_slots_of_a_flagged_entry() {
+        let mut queue = Queue::<StringId>::default();
+        queue.push_id(StringId(1));
+        queue.push_id_with_flags(StringId(2), SolvableFlags::empty());
+        queue.push_id(StringId(3));
+
+        assert!(queue.remove_first(StringId(2)));
+        assert!(!queue.contains(StringId(2)));
+
+        // No dangling flag word left behind: both remaining entries round-trip cleanly.
+        assert_eq!(queue.len(), 2);
+        assert_eq!(queue.pop_front().map(|id| id.0), Some(1));
+        assert_eq!(queue.pop_front().map(|id| id.0), Some(3));
+        assert_eq!(queue.pop_front(), None);
+    }
+
+    #[test]
+    fn retain_drops_a_flagged_entry_without_leaving_a_dangling_flag() {
+        let mut queue = Queue::<StringId>::default();
+        queue.push_id(StringId(1));
+        queue.push_id_with_flags(StringId(2), SolvableFlags::empty());
+        queue.push_id(StringId(3));
+
+        queue.retain(|id| id.0 != 2);
+
+        assert_eq!(queue.len(), 2);
+        assert_eq!(queue.pop_front().map(|id| id.0), Some(1));
+        assert_eq!(queue.pop_front().map(|id| id.0), Some(3));
+        assert_eq!(queue.pop_front(), None);
+    }
+
+    #[test]
+    fn clear_forgets_previously_pushed_flagged_entries() {
+        let mut queue = Queue::<StringId>::default();
+        queue.push_id_with_flags(StringId(1), SolvableFlags::empty());
+        queue.clear();
+
+        // Rebuilt from scratch with only plain ids; nothing about the flagged entry pushed
+        // before `clear()` should linger.
+        queue.push_id(StringId(2));
+        queue.push_id(StringId(3));
+
+        assert_eq!(queue.len(), 2);
+        assert!(queue.remove_first(StringId(2)));
+        assert_eq!(queue.pop_front().map(|id| id.0), Some(3));
+    }
+
+    #[test]
+    fn extend_pushes_all_ids_in_order() {
+        let mut queue = Queue::<StringId>::default();
+        queue.push_id(StringId(1));
+        queue.extend(vec![StringId(2), StringId(3)]);
+
+        assert_eq!(queue.len(), 3);
+        assert_eq!(queue.pop_front().map(|id| id.0), Some(1));
+        assert_eq!(queue.pop_front().map(|id| id.0), Some(2));
+        assert_eq!(queue.pop_front().map(|id| id.0), Some(3));
+    }
+
+    #[test]
+    fn from_iter_collects_into_a_queue() {
+        let queue: Queue<StringId> = vec![StringId(1), StringId(2), StringId(3)]
+            .into_iter()
+            .collect();
+
+        assert_eq!(queue.len(), 3);
+    }
+
+    #[test]
+    fn queue_ref_iter_round_trips_a_non_solvable_id_type() {
+        let mut queue = Queue::<StringId>::default();
+        queue.push_id(StringId(1));
+        queue.push_id(StringId(2));
+        queue.push_id(StringId(3));
+
+        // Safe: `queue` outlives the `QueueRef` and is never freed while it's borrowed.
+        let queue_ref: QueueRef<'_, StringId> =
+            unsafe { QueueRef::from_ffi_queue(&queue, queue.queue) };
+
+        let ids: Vec<_> = queue_ref.iter().map(|id| id.0).collect();
+        assert_eq!(ids, vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn with_capacity_and_reserve_start_empty() {
+        let mut queue = Queue::<StringId>::with_capacity(16);
+        assert!(queue.is_empty());
+
+        queue.reserve(16);
+        assert!(queue.is_empty());
+
+        queue.push_id(StringId(1));
+        assert_eq!(queue.len(), 1);
+    }
 }